@@ -0,0 +1,148 @@
+//! Proc-macro support for `ercp_basic` routers.
+//!
+//! The router's `route` match arm and its `firmware_version`/`description`
+//! impls were hand-maintained, which let handlers be written but forgotten in
+//! the dispatch table. This crate generates both from annotations.
+//!
+//! A derive cannot see the handler methods (they live in a separate `impl`
+//! block), so the dispatch table is generated by the [`macro@router`]
+//! attribute placed on the handler `impl`; `#[command(code = ..)]` marks each
+//! handler and `#[ercp(version = .., description = ..)]` fills in the metadata.
+//!
+//! ```ignore
+//! #[ercp_basic_derive::router(context = DriveableResources)]
+//! #[ercp(version = env!("CARGO_PKG_VERSION"), description = "demo")]
+//! impl CustomRouter {
+//!     #[command(code = 0x20)]
+//!     fn led_on(&mut self, cx: &mut DriveableResources) -> Option<Command> { .. }
+//! }
+//! ```
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_macro_input, Expr, ImplItem, ItemImpl, Token, Type,
+};
+
+/// Arguments to the `#[router(context = ..)]` attribute.
+struct RouterArgs {
+    context: Type,
+}
+
+impl Parse for RouterArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        // Accept `context = SomeType`.
+        let ident: syn::Ident = input.parse()?;
+        if ident != "context" {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "expected `context = <Type>`",
+            ));
+        }
+        input.parse::<Token![=]>()?;
+        let context = input.parse()?;
+        Ok(Self { context })
+    }
+}
+
+/// Generates `Router::route` plus `firmware_version`/`description` from the
+/// `#[command]`/`#[ercp]` annotations on a handler `impl`.
+#[proc_macro_attribute]
+pub fn router(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as RouterArgs);
+    let mut input = parse_macro_input!(item as ItemImpl);
+
+    let self_ty = &input.self_ty;
+    let context = &args.context;
+
+    let mut arms = Vec::new();
+    let mut version = None;
+    let mut description = None;
+
+    // Pull `#[ercp(..)]` off the impl block itself.
+    input.attrs.retain(|attr| {
+        if attr.path().is_ident("ercp") {
+            let _ = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("version") {
+                    version = Some(meta.value()?.parse::<Expr>()?);
+                } else if meta.path.is_ident("description") {
+                    description = Some(meta.value()?.parse::<Expr>()?);
+                }
+                Ok(())
+            });
+            false
+        } else {
+            true
+        }
+    });
+
+    // Collect `#[command(code = ..)]` handlers, stripping the marker so the
+    // emitted impl compiles.
+    for item in &mut input.items {
+        if let ImplItem::Fn(method) = item {
+            let mut code = None;
+            method.attrs.retain(|attr| {
+                if attr.path().is_ident("command") {
+                    let _ = attr.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("code") {
+                            // Accept a literal (`0x20`) or a named const
+                            // (`LED_ON`); both are valid match patterns.
+                            code = Some(meta.value()?.parse::<Expr>()?);
+                        }
+                        Ok(())
+                    });
+                    false
+                } else {
+                    true
+                }
+            });
+
+            if let Some(code) = code {
+                let name = &method.sig.ident;
+                arms.push(quote! {
+                    #code => self.#name(command, cx),
+                });
+            }
+        }
+    }
+
+    let version = version.unwrap_or_else(|| {
+        syn::parse_quote!(concat!(
+            env!("CARGO_PKG_NAME"),
+            " ",
+            env!("CARGO_PKG_VERSION")
+        ))
+    });
+    let description =
+        description.unwrap_or_else(|| syn::parse_quote!(env!("CARGO_PKG_DESCRIPTION")));
+
+    let expanded = quote! {
+        #input
+
+        impl ::ercp_basic::Router<RX_MAX_LEN> for #self_ty {
+            type Context = #context;
+
+            fn route(
+                &mut self,
+                command: ::ercp_basic::Command,
+                cx: &mut Self::Context,
+            ) -> ::core::option::Option<::ercp_basic::Command> {
+                match command.code() {
+                    #(#arms)*
+                    _ => self.default_routes(command),
+                }
+            }
+
+            fn firmware_version(&self) -> &str {
+                #version
+            }
+
+            fn description(&self) -> &str {
+                #description
+            }
+        }
+    };
+
+    expanded.into()
+}
@@ -0,0 +1,176 @@
+//! Ed25519 authentication for privileged ERCP commands.
+//!
+//! Sensitive routes (`COUNTER_SET`, firmware commit, a future `REBOOT`) must
+//! not execute unconditionally. The device holds a compile-time
+//! [`VERIFYING_KEY`]; a privileged command carries a detached signature in the
+//! tail of its value, and a monotonic nonce rejects replays.
+//!
+//! # Signed layout
+//!
+//! There is exactly one signing layout, used for both commands and firmware
+//! images. The signed message is:
+//!
+//! ```text
+//! code (1) || body_len (1) || nonce (4, LE) || body
+//! ```
+//!
+//! - `code` is the ERCP command code.
+//! - `body_len` is the length of `nonce || body` as a single byte.
+//! - `nonce` is a monotonically increasing 4-byte little-endian counter.
+//! - `body` is the command payload, or — at `FW_COMMIT` time — the digest of
+//!   the whole written image.
+//!
+//! The 64-byte detached signature over that message is appended after the
+//! signed region. For a privileged command the value on the wire is therefore
+//! `nonce || payload || signature`; for a firmware commit it is
+//! `nonce || signature`, the image digest being reconstructed on-device.
+//!
+//! # Replay window
+//!
+//! [`Authenticator`] keeps the last accepted nonce in RAM only, so a reset
+//! returns it to zero and previously-captured signed commands could replay. A
+//! production device must persist the nonce across reboots (e.g. in a flash
+//! word or backup register) and seed [`Authenticator::new`] with it.
+
+use ercp_basic::Command;
+
+use salty::{PublicKey, Signature};
+
+/// The detached signature length appended to a signed command's value.
+const SIGNATURE_LEN: usize = 64;
+
+/// The replay-protection nonce prefixed to the signed region.
+const NONCE_LEN: usize = 4;
+
+/// Upper bound on a signed message: the 2-byte `code || body_len` header plus
+/// the largest possible signed region (a full ERCP command value).
+const MAX_MESSAGE_LEN: usize = 2 + 255;
+
+/// The device's compile-time verifying key.
+///
+/// Replace these bytes with the public half of the signing key held by the
+/// host tooling.
+const VERIFYING_KEY: [u8; 32] = *include_bytes!("verifying_key.bin");
+
+/// Verifies the signature and replay nonce of a privileged command.
+pub struct Authenticator {
+    last_nonce: u32,
+}
+
+impl Default for Authenticator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Authenticator {
+    /// Creates an authenticator seeded with the last accepted nonce.
+    ///
+    /// Pass a nonce recovered from persistent storage; `0` is correct only on
+    /// the very first boot (see the module-level replay-window note).
+    pub fn new() -> Self {
+        Self { last_nonce: 0 }
+    }
+
+    /// Strips and checks the signature of a privileged command whose value is
+    /// `nonce || payload || signature`, returning the inner payload.
+    pub fn verify<'a>(&mut self, command: &'a Command) -> Result<&'a [u8], ()> {
+        let value = command.value();
+
+        if value.len() < NONCE_LEN + SIGNATURE_LEN {
+            return Err(());
+        }
+
+        let (signed, signature) = value.split_at(value.len() - SIGNATURE_LEN);
+        let (nonce_bytes, payload) = signed.split_at(NONCE_LEN);
+
+        self.check(command.code(), signed, signature)?;
+        let _ = nonce_bytes;
+        Ok(payload)
+    }
+
+    /// Checks a firmware-commit signature over the digest of the written
+    /// image.
+    ///
+    /// The commit command value is `nonce || signature`; the signed body is
+    /// the `digest` reconstructed on-device, so the signature attests to the
+    /// bytes actually programmed rather than to the command itself.
+    pub fn verify_image(
+        &mut self,
+        command: &Command,
+        digest: &[u8],
+    ) -> Result<(), ()> {
+        let value = command.value();
+
+        if value.len() != NONCE_LEN + SIGNATURE_LEN {
+            return Err(());
+        }
+
+        let (nonce_bytes, signature) = value.split_at(NONCE_LEN);
+
+        // Rebuild the signed region: nonce || image digest.
+        let mut signed = [0u8; NONCE_LEN + 64];
+        if digest.len() != 64 {
+            return Err(());
+        }
+        signed[0..NONCE_LEN].copy_from_slice(nonce_bytes);
+        signed[NONCE_LEN..].copy_from_slice(digest);
+
+        self.check(command.code(), &signed, signature)
+    }
+
+    /// Verifies `signature` over `code || signed.len() || signed` and, on
+    /// success, advances the replay nonce.
+    ///
+    /// `signed` is `nonce (4, LE) || body`.
+    fn check(
+        &mut self,
+        code: u8,
+        signed: &[u8],
+        signature: &[u8],
+    ) -> Result<(), ()> {
+        let nonce = u32::from_le_bytes([signed[0], signed[1], signed[2], signed[3]]);
+
+        // Reject replays and out-of-order nonces.
+        if nonce <= self.last_nonce {
+            return Err(());
+        }
+
+        if !verify_signature(code, signed, signature) {
+            return Err(());
+        }
+
+        self.last_nonce = nonce;
+        Ok(())
+    }
+}
+
+/// Verifies a detached signature over `code || signed.len() || signed`.
+pub fn verify_signature(code: u8, signed: &[u8], signature: &[u8]) -> bool {
+    let signature: &[u8; SIGNATURE_LEN] = match signature.try_into() {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+
+    let key = match PublicKey::try_from(&VERIFYING_KEY) {
+        Ok(key) => key,
+        Err(_) => return false,
+    };
+
+    let signature = Signature::from(signature);
+
+    // Bind the signature to this exact command: code, the signed-region
+    // length, then the signed region (nonce || body) itself. The scratch
+    // buffer spans any command value (not just an image digest), so larger
+    // signed payloads are not spuriously rejected.
+    let mut message = [0u8; MAX_MESSAGE_LEN];
+    let end = 2 + signed.len();
+    if end > message.len() {
+        return false;
+    }
+    message[0] = code;
+    message[1] = signed.len() as u8;
+    message[2..end].copy_from_slice(signed);
+
+    key.verify(&message[..end], &signature).is_ok()
+}
@@ -0,0 +1,64 @@
+//! A `postcard`-backed codec for typed command payloads.
+//!
+//! `counter_get`/`counter_set` used to index `command.value()[0]` and poke
+//! bytes into the router buffer by hand, which does not scale past a single
+//! `u8`. This codec lets handlers exchange structured Rust types instead, by
+//! serialising into a fixed-capacity buffer bounded by `TX_MAX_LEN` — no
+//! heap, no `alloc`.
+//!
+//! The API is offered as the [`CommandCodec`] extension trait rather than
+//! inherent `Command::encode`/`Command::decode` methods, because `Command` is
+//! a foreign type from `ercp_basic` and inherent methods cannot be added to it
+//! from this crate. `encode` takes the router's TX buffer explicitly since a
+//! borrowed-slice `Command` cannot own the serialised bytes.
+//!
+//! Only compiled with the `codec` feature enabled.
+#![cfg(feature = "codec")]
+
+use ercp_basic::Command;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// A codec error.
+pub enum CodecError {
+    /// The value could not be (de)serialised.
+    Postcard,
+    /// The serialised value did not fit in the target buffer.
+    TooLong,
+}
+
+impl From<postcard::Error> for CodecError {
+    fn from(_: postcard::Error) -> Self {
+        CodecError::Postcard
+    }
+}
+
+/// Typed (de)serialisation helpers for [`Command`].
+pub trait CommandCodec<'a> {
+    /// Serialises `value` into `buffer` and wraps it in a command with `code`.
+    ///
+    /// `buffer` is the router's TX buffer; the returned command borrows it.
+    fn encode<T: Serialize>(
+        code: u8,
+        value: &T,
+        buffer: &'a mut [u8],
+    ) -> Result<Command<'a>, CodecError>;
+
+    /// Deserialises the command's payload into a `T`.
+    fn decode<T: DeserializeOwned>(&self) -> Result<T, CodecError>;
+}
+
+impl<'a> CommandCodec<'a> for Command<'a> {
+    fn encode<T: Serialize>(
+        code: u8,
+        value: &T,
+        buffer: &'a mut [u8],
+    ) -> Result<Command<'a>, CodecError> {
+        let used = postcard::to_slice(value, buffer)?;
+        Command::new(code, used).map_err(|_| CodecError::TooLong)
+    }
+
+    fn decode<T: DeserializeOwned>(&self) -> Result<T, CodecError> {
+        Ok(postcard::from_bytes(self.value())?)
+    }
+}
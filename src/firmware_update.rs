@@ -0,0 +1,135 @@
+//! A DFU-style firmware-update subsystem exposed over ERCP.
+//!
+//! This gives every device over-the-air update without hand-rolling commands:
+//! `CustomRouter` delegates the `FW_*` command codes to a small state machine
+//! here, which in turn drives a [`FirmwareUpdater`]. The flow mirrors
+//! embassy-boot's firmware-updater: erase the inactive slot once, write its
+//! chunks sequentially, then mark it bootable on commit.
+
+/// A backend able to program the inactive firmware slot.
+///
+/// Implementors own the erase-once/write-many/finalize sequence for a concrete
+/// flash region; the ERCP layer only tracks framing and ordering.
+pub trait FirmwareUpdater {
+    /// Erases the inactive slot and returns its size in bytes.
+    fn erase_region(&mut self) -> Result<u32, ()>;
+
+    /// Writes `data` at `offset` within the slot.
+    fn write_chunk(&mut self, offset: u32, data: &[u8]) -> Result<(), ()>;
+
+    /// Marks the freshly written slot as bootable.
+    fn finalize(&mut self) -> Result<(), ()>;
+}
+
+use sha2::{Digest, Sha512};
+
+/// The length of the image digest covered by the commit signature.
+pub const DIGEST_LEN: usize = 64;
+
+/// Tracks the progress of an in-flight update so out-of-order or gapped
+/// `FW_WRITE` frames can be rejected.
+///
+/// A running digest of every written byte is kept so the `FW_COMMIT`
+/// signature can attest to the image actually programmed, not just to the
+/// commit command.
+pub struct FirmwareUpdate {
+    active: bool,
+    next_offset: u32,
+    digest: Sha512,
+}
+
+impl Default for FirmwareUpdate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FirmwareUpdate {
+    /// Creates an idle update tracker.
+    pub fn new() -> Self {
+        Self {
+            active: false,
+            next_offset: 0,
+            digest: Sha512::new(),
+        }
+    }
+
+    /// Begins an update: erases the slot and resets the write cursor and the
+    /// image digest.
+    pub fn begin(
+        &mut self,
+        updater: &mut impl FirmwareUpdater,
+    ) -> Result<u32, ()> {
+        let size = updater.erase_region()?;
+        self.active = true;
+        self.next_offset = 0;
+        self.digest = Sha512::new();
+        Ok(size)
+    }
+
+    /// Writes the next chunk, enforcing that `offset` matches the expected
+    /// next offset so no bytes are skipped or rewritten.
+    pub fn write(
+        &mut self,
+        updater: &mut impl FirmwareUpdater,
+        offset: u32,
+        data: &[u8],
+    ) -> Result<(), UpdateError> {
+        if !self.active {
+            return Err(UpdateError::NotStarted);
+        }
+
+        if offset != self.next_offset {
+            return Err(UpdateError::OutOfOrder);
+        }
+
+        updater
+            .write_chunk(offset, data)
+            .map_err(|()| UpdateError::Backend)?;
+        self.digest.update(data);
+        self.next_offset += data.len() as u32;
+        Ok(())
+    }
+
+    /// Returns the digest of every byte written so far.
+    ///
+    /// Used to reconstruct the signed region at commit time without buffering
+    /// the whole image.
+    pub fn image_digest(&self) -> [u8; DIGEST_LEN] {
+        self.digest.clone().finalize().into()
+    }
+
+    /// Finalises the update, marking the slot bootable.
+    ///
+    /// The caller must have verified the image signature first (see
+    /// [`image_digest`](Self::image_digest)); this only drives the backend.
+    pub fn commit(
+        &mut self,
+        updater: &mut impl FirmwareUpdater,
+    ) -> Result<(), UpdateError> {
+        if !self.active {
+            return Err(UpdateError::NotStarted);
+        }
+
+        updater.finalize().map_err(|()| UpdateError::Backend)?;
+        self.active = false;
+        Ok(())
+    }
+
+    /// Aborts an in-flight update without touching the active slot.
+    pub fn abort(&mut self) {
+        self.active = false;
+        self.next_offset = 0;
+        self.digest = Sha512::new();
+    }
+}
+
+/// Why a firmware-update step was rejected.
+pub enum UpdateError {
+    /// A `FW_WRITE`/`FW_COMMIT` arrived without a preceding `FW_BEGIN`.
+    NotStarted,
+    /// The chunk offset did not match the expected next offset.
+    OutOfOrder,
+    /// The underlying flash backend failed.
+    Backend,
+}
@@ -5,25 +5,90 @@ use stm32l4xx_hal::{
     prelude::*,
 };
 
-use ercp_basic::{ack, command::nack_reason, nack, Command, Router};
+use ercp_basic::{ack, command::nack_reason, nack, Command};
+
+use ercp_basic_derive::router;
+
+pub mod async_adapter;
+pub mod auth;
+pub mod codec;
+pub mod firmware_update;
+pub mod usb_adapter;
+
+use auth::Authenticator;
+use firmware_update::{FirmwareUpdate, FirmwareUpdater, UpdateError};
 
 /// The board LED.
 type Led = PA5<Output<PushPull>>;
 
+/// A structured counter payload exchanged via the typed codec.
+///
+/// Shows off exchanging a Rust type instead of poking single bytes; only
+/// built when the `codec` feature is enabled.
+#[cfg(feature = "codec")]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct CounterState {
+    pub value: u16,
+    pub min: u16,
+    pub max: u16,
+}
+
 /// Resources that are driveable via ERCP.
 pub struct DriveableResources {
     led: Led,
     counter: u8,
+    flash: SlotFlash,
+}
+
+/// A stand-in firmware-update backend for the example.
+///
+/// A real board would program the inactive flash bank here; we only track the
+/// cursor and log, which is enough to exercise the `FW_*` routes end to end.
+pub struct SlotFlash {
+    size: u32,
+}
+
+impl SlotFlash {
+    const SLOT_SIZE: u32 = 128 * 1024;
+
+    fn new() -> Self {
+        Self {
+            size: Self::SLOT_SIZE,
+        }
+    }
+}
+
+impl FirmwareUpdater for SlotFlash {
+    fn erase_region(&mut self) -> Result<u32, ()> {
+        defmt::info!("Erasing inactive slot ({} bytes)", self.size);
+        Ok(self.size)
+    }
+
+    fn write_chunk(&mut self, offset: u32, data: &[u8]) -> Result<(), ()> {
+        defmt::debug!("Flashing {} bytes at offset {}", data.len(), offset);
+        Ok(())
+    }
+
+    fn finalize(&mut self) -> Result<(), ()> {
+        defmt::info!("Marking new slot bootable");
+        Ok(())
+    }
 }
 
 /// Our custom ERCP router.
 pub struct CustomRouter {
     buffer: [u8; TX_MAX_LEN],
+    firmware_update: FirmwareUpdate,
+    authenticator: Authenticator,
 }
 
 impl DriveableResources {
     pub fn new(led: Led) -> Self {
-        Self { led, counter: 0 }
+        Self {
+            led,
+            counter: 0,
+            flash: SlotFlash::new(),
+        }
     }
 }
 
@@ -31,93 +96,133 @@ impl Default for CustomRouter {
     fn default() -> Self {
         Self {
             buffer: [0; TX_MAX_LEN],
+            firmware_update: FirmwareUpdate::new(),
+            authenticator: Authenticator::new(),
         }
     }
 }
 
-impl Router<RX_MAX_LEN> for CustomRouter {
-    type Context = DriveableResources;
-
-    fn route(
-        &mut self,
-        command: Command,
-        cx: &mut Self::Context,
-    ) -> Option<Command> {
-        match command.code() {
-            // Override the route method to add our routes.
-            LED_ON => self.led_on(&mut cx.led),
-            LED_OFF => self.led_off(&mut cx.led),
-            COUNTER_GET => self.counter_get(cx.counter),
-            COUNTER_SET => self.counter_set(command, &mut cx.counter),
-            COUNTER_INC => self.counter_inc(&mut cx.counter),
-            COUNTER_DEC => self.counter_dec(&mut cx.counter),
-
-            // Always end with default routes.
-            _ => self.default_routes(command),
-        }
-    }
-
-    // Customise the firmware version & description.
-
-    // TODO: Use a macro instead to generate this.
-    fn firmware_version(&self) -> &str {
-        concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"))
-    }
-
-    // TODO: Use a macro instead to generate this.
-    fn description(&self) -> &str {
-        env!("CARGO_PKG_DESCRIPTION")
-    }
-}
-
 impl CustomRouter {
     /// Creates a new router.
     pub fn new() -> Self {
         Self::default()
     }
+}
 
+// The `route` dispatch table and the `firmware_version`/`description` impls
+// are generated from the `#[command]` annotations below, so a handler can no
+// longer be written but forgotten in the dispatch table.
+#[router(context = DriveableResources)]
+impl CustomRouter {
     // Command handlers are here.
 
-    fn led_on(&mut self, led: &mut Led) -> Option<Command> {
+    #[command(code = LED_ON)]
+    fn led_on(&mut self, _command: Command, cx: &mut DriveableResources) -> Option<Command> {
         defmt::info!("Led on");
-        led.set_high().ok();
+        cx.led.set_high().ok();
         Some(ack!())
     }
 
-    fn led_off(&mut self, led: &mut Led) -> Option<Command> {
+    #[command(code = LED_OFF)]
+    fn led_off(&mut self, _command: Command, cx: &mut DriveableResources) -> Option<Command> {
         defmt::info!("Led off");
-        led.set_low().ok();
+        cx.led.set_low().ok();
         Some(ack!())
     }
 
-    fn counter_get(&mut self, counter: u8) -> Option<Command> {
-        defmt::info!("Counter = {}", counter);
-        self.buffer[0] = counter;
-        let reply =
-            Command::new(COUNTER_GET_REPLY, &self.buffer[0..1]).unwrap();
-        Some(reply)
-    }
-
-    fn counter_set(
-        &mut self,
-        command: Command,
-        counter: &mut u8,
-    ) -> Option<Command> {
-        if command.length() == 1 {
-            defmt::info!("Setting the counter to {}", command.value()[0]);
-            *counter = command.value()[0];
-            Some(ack!())
-        } else {
-            defmt::warn!("Invalid arguments");
-            Some(nack!(nack_reason::INVALID_ARGUMENTS))
+    #[command(code = COUNTER_GET)]
+    fn counter_get(&mut self, _command: Command, cx: &mut DriveableResources) -> Option<Command> {
+        defmt::info!("Counter = {}", cx.counter);
+
+        // With the codec feature we reply with a structured `CounterState`;
+        // otherwise we fall back to the single-byte reply.
+        #[cfg(feature = "codec")]
+        {
+            use crate::codec::CommandCodec;
+
+            let state = CounterState {
+                value: cx.counter as u16,
+                min: u8::MIN as u16,
+                max: u8::MAX as u16,
+            };
+
+            match Command::encode(COUNTER_GET_REPLY, &state, &mut self.buffer) {
+                Ok(reply) => Some(reply),
+                Err(_) => Some(nack!(OUT_OF_BOUNDS)),
+            }
+        }
+
+        #[cfg(not(feature = "codec"))]
+        {
+            self.buffer[0] = cx.counter;
+            Some(Command::new(COUNTER_GET_REPLY, &self.buffer[0..1]).unwrap())
+        }
+    }
+
+    #[command(code = COUNTER_SET)]
+    fn counter_set(&mut self, command: Command, cx: &mut DriveableResources) -> Option<Command> {
+        // COUNTER_SET is privileged: the value must carry a valid signature.
+        let payload = match self.authenticator.verify(&command) {
+            Ok(payload) => payload,
+            Err(()) => {
+                defmt::warn!("Unauthorized COUNTER_SET");
+                return Some(nack!(UNAUTHORIZED));
+            }
+        };
+
+        // With the codec feature the signed payload carries a structured
+        // `CounterState`; otherwise it is a single byte.
+        #[cfg(feature = "codec")]
+        {
+            use crate::codec::CommandCodec;
+
+            let decoded = Command::new(COUNTER_SET, payload)
+                .ok()
+                .and_then(|command| command.decode::<CounterState>().ok());
+
+            match decoded {
+                Some(state) => {
+                    // The counter is a `u8`: reject values that would not fit
+                    // or that fall outside the advertised range rather than
+                    // silently truncating them.
+                    if state.value < state.min
+                        || state.value > state.max
+                        || state.value > u8::MAX as u16
+                    {
+                        defmt::warn!("Counter value {} out of bounds", state.value);
+                        return Some(nack!(OUT_OF_BOUNDS));
+                    }
+
+                    defmt::info!("Setting the counter to {}", state.value);
+                    cx.counter = state.value as u8;
+                    Some(ack!())
+                }
+                None => {
+                    defmt::warn!("Invalid arguments");
+                    Some(nack!(nack_reason::INVALID_ARGUMENTS))
+                }
+            }
+        }
+
+        #[cfg(not(feature = "codec"))]
+        {
+            if payload.len() == 1 {
+                defmt::info!("Setting the counter to {}", payload[0]);
+                cx.counter = payload[0];
+                Some(ack!())
+            } else {
+                defmt::warn!("Invalid arguments");
+                Some(nack!(nack_reason::INVALID_ARGUMENTS))
+            }
         }
     }
 
-    fn counter_inc(&mut self, counter: &mut u8) -> Option<Command> {
-        match counter.checked_add(1) {
+    #[command(code = COUNTER_INC)]
+    fn counter_inc(&mut self, _command: Command, cx: &mut DriveableResources) -> Option<Command> {
+        match cx.counter.checked_add(1) {
             Some(value) => {
                 defmt::info!("Increasing the counter to {}", value);
-                *counter = value;
+                cx.counter = value;
                 Some(ack!())
             }
 
@@ -128,11 +233,12 @@ impl CustomRouter {
         }
     }
 
-    fn counter_dec(&mut self, counter: &mut u8) -> Option<Command> {
-        match counter.checked_sub(1) {
+    #[command(code = COUNTER_DEC)]
+    fn counter_dec(&mut self, _command: Command, cx: &mut DriveableResources) -> Option<Command> {
+        match cx.counter.checked_sub(1) {
             Some(value) => {
                 defmt::info!("Decreasing the counter to {}", value);
-                *counter = value;
+                cx.counter = value;
                 Some(ack!())
             }
 
@@ -142,6 +248,81 @@ impl CustomRouter {
             }
         }
     }
+
+    // Firmware-update handlers.
+
+    #[command(code = FW_BEGIN)]
+    fn fw_begin(&mut self, _command: Command, cx: &mut DriveableResources) -> Option<Command> {
+        match self.firmware_update.begin(&mut cx.flash) {
+            Ok(size) => {
+                defmt::info!("Firmware update started, slot size = {}", size);
+                self.buffer[0..4].copy_from_slice(&size.to_le_bytes());
+                Some(Command::new(FW_BEGIN_REPLY, &self.buffer[0..4]).unwrap())
+            }
+
+            Err(()) => {
+                defmt::error!("Could not erase the firmware slot");
+                Some(nack!(FW_ERROR))
+            }
+        }
+    }
+
+    #[command(code = FW_WRITE)]
+    fn fw_write(&mut self, command: Command, cx: &mut DriveableResources) -> Option<Command> {
+        let value = command.value();
+
+        if value.len() < 4 {
+            defmt::warn!("Invalid arguments");
+            return Some(nack!(nack_reason::INVALID_ARGUMENTS));
+        }
+
+        let offset = u32::from_le_bytes([value[0], value[1], value[2], value[3]]);
+
+        match self.firmware_update.write(&mut cx.flash, offset, &value[4..]) {
+            Ok(()) => Some(ack!()),
+
+            Err(UpdateError::OutOfOrder) => {
+                defmt::warn!("Out-of-order firmware chunk at offset {}", offset);
+                Some(nack!(FW_OUT_OF_ORDER))
+            }
+
+            Err(UpdateError::NotStarted) => Some(nack!(FW_NOT_STARTED)),
+            Err(UpdateError::Backend) => Some(nack!(FW_ERROR)),
+        }
+    }
+
+    #[command(code = FW_COMMIT)]
+    fn fw_commit(&mut self, command: Command, cx: &mut DriveableResources) -> Option<Command> {
+        // Only mark the slot bootable once the signature over the digest of
+        // the bytes actually written verifies against the device's
+        // compile-time key. This is what makes an unauthenticated FW_WRITE
+        // harmless: the attacker cannot produce a valid signature over the
+        // resulting image digest, so the slot never becomes bootable.
+        let digest = self.firmware_update.image_digest();
+        if self.authenticator.verify_image(&command, &digest).is_err() {
+            defmt::warn!("Unauthorized firmware commit");
+            self.firmware_update.abort();
+            return Some(nack!(UNAUTHORIZED));
+        }
+
+        match self.firmware_update.commit(&mut cx.flash) {
+            Ok(()) => {
+                defmt::info!("Firmware update committed");
+                Some(ack!())
+            }
+
+            Err(UpdateError::NotStarted) => Some(nack!(FW_NOT_STARTED)),
+            Err(_) => Some(nack!(FW_ERROR)),
+        }
+    }
+
+    #[command(code = FW_ABORT)]
+    fn fw_abort(&mut self, _command: Command, cx: &mut DriveableResources) -> Option<Command> {
+        let _ = cx;
+        defmt::info!("Firmware update aborted");
+        self.firmware_update.abort();
+        Some(ack!())
+    }
 }
 
 // Rx & Tx buffer sizes.
@@ -157,4 +338,17 @@ const COUNTER_SET: u8 = 0x32;
 const COUNTER_INC: u8 = 0x33;
 const COUNTER_DEC: u8 = 0x34;
 
+// Firmware-update commands.
+const FW_BEGIN: u8 = 0x40;
+const FW_BEGIN_REPLY: u8 = 0x41;
+const FW_WRITE: u8 = 0x42;
+const FW_COMMIT: u8 = 0x43;
+const FW_ABORT: u8 = 0x44;
+
 const OUT_OF_BOUNDS: u8 = 0xFF;
+
+// Firmware-update nack reasons.
+const FW_NOT_STARTED: u8 = 0xF0;
+const FW_OUT_OF_ORDER: u8 = 0xF1;
+const FW_ERROR: u8 = 0xF2;
+const UNAUTHORIZED: u8 = 0xF3;
@@ -0,0 +1,67 @@
+//! A USB CDC-ACM adapter for ERCP.
+//!
+//! This mirrors `ercp_basic::adapter::SerialAdapter`, but drives a
+//! `usbd_serial::SerialPort` instead of a UART, so boards that only expose a
+//! native USB connector can still speak ERCP to a host. It implements the same
+//! `Adapter` trait the rest of the stack (`handle_data`, frame transmission)
+//! relies on.
+
+use ercp_basic::adapter::Adapter;
+
+use usb_device::{bus::UsbBus, UsbError};
+use usbd_serial::SerialPort;
+
+/// Errors the USB adapter can surface to the ERCP stack.
+pub enum Error {
+    /// The endpoint was momentarily full or empty; retry on the next
+    /// `UsbDevice::poll`.
+    WouldBlock,
+    /// The CDC-ACM port reported a genuine transfer failure.
+    Usb(UsbError),
+}
+
+/// An ERCP adapter backed by a USB CDC-ACM serial port.
+pub struct UsbSerialAdapter<'a, B: UsbBus> {
+    port: SerialPort<'a, B>,
+}
+
+impl<'a, B: UsbBus> UsbSerialAdapter<'a, B> {
+    /// Builds a new adapter wrapping the given CDC-ACM port.
+    pub fn new(port: SerialPort<'a, B>) -> Self {
+        Self { port }
+    }
+
+    /// Returns the wrapped port, e.g. so it can be driven by `UsbDevice::poll`.
+    pub fn port(&mut self) -> &mut SerialPort<'a, B> {
+        &mut self.port
+    }
+}
+
+impl<'a, B: UsbBus> Adapter for UsbSerialAdapter<'a, B> {
+    type Error = Error;
+
+    fn read(&mut self) -> Result<Option<u8>, Self::Error> {
+        let mut byte = [0; 1];
+
+        // Drain one byte out of the CDC RX FIFO. A momentarily empty endpoint
+        // reports `WouldBlock`, which maps to "no data yet" just like a UART
+        // with no pending character.
+        match self.port.read(&mut byte) {
+            Ok(_) => Ok(Some(byte[0])),
+            Err(UsbError::WouldBlock) => Ok(None),
+            Err(e) => Err(Error::Usb(e)),
+        }
+    }
+
+    fn write(&mut self, byte: u8) -> Result<(), Self::Error> {
+        // Push outgoing bytes back through the TX endpoint. When it is full the
+        // endpoint reports `WouldBlock`; surface that so the USB task can retry
+        // after the next `UsbDevice::poll` drains it, rather than spinning here
+        // (which would wedge the executor, since the poll can never run).
+        match self.port.write(&[byte]) {
+            Ok(_) => Ok(()),
+            Err(UsbError::WouldBlock) => Err(Error::WouldBlock),
+            Err(e) => Err(Error::Usb(e)),
+        }
+    }
+}